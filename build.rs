@@ -30,10 +30,12 @@ fn main() -> Result<(), std::io::Error> {
 		.filter(|e| e.path().is_file())
 		.map(|e| e.path())
 		.map(|path| {
+			let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
 			format!(
-				"(\"{}\", &include_bytes!(\"{}\")[..]), ",
+				"(\"{}\", (&include_bytes!(\"{}\")[..], \"{}\")), ",
 				path.strip_prefix(&frontend_dist_dir).unwrap().display(),
 				path.display(),
+				file_extension_to_mime(extension),
 			)
 		})
 		.collect::<String>();
@@ -46,6 +48,28 @@ fn main() -> Result<(), std::io::Error> {
 	Ok(())
 }
 
+fn file_extension_to_mime(ext: &str) -> &'static str {
+	match ext {
+		"html" | "htm" => "text/html",
+		"css" => "text/css",
+		"js" | "mjs" => "application/javascript",
+		"json" | "map" => "application/json",
+		"svg" => "image/svg+xml",
+		"png" => "image/png",
+		"jpg" | "jpeg" => "image/jpeg",
+		"gif" => "image/gif",
+		"ico" => "image/x-icon",
+		"wasm" => "application/wasm",
+		"woff" => "font/woff",
+		"woff2" => "font/woff2",
+		"ttf" => "font/ttf",
+		"otf" => "font/otf",
+		"txt" => "text/plain",
+		"xml" => "application/xml",
+		_ => "application/octet-stream",
+	}
+}
+
 struct DirIter {
 	stack: Vec<Result<DirEntry, std::io::Error>>,
 }
@@ -0,0 +1,140 @@
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{get, web, HttpResponse};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::output_format::OutputFormat;
+use crate::ScanServiceError;
+
+const ARCHIVE_DIR: &str = "scan_archive";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ArchiveMetadata {
+	pub hash: String,
+	pub timestamp: u64,
+	pub width: u32,
+	pub height: u32,
+	pub format: String,
+	pub size: u64,
+}
+
+fn archive_dir() -> anyhow::Result<PathBuf> {
+	let dir = PathBuf::from(ARCHIVE_DIR);
+	fs::create_dir_all(&dir)?;
+	Ok(dir)
+}
+
+fn data_path(dir: &Path, hash: &str) -> PathBuf {
+	dir.join(format!("{hash}.bin"))
+}
+
+fn metadata_path(dir: &Path, hash: &str) -> PathBuf {
+	dir.join(format!("{hash}.json"))
+}
+
+/// SHA-256 hex digests are exactly 64 lowercase hex characters; anything else is rejected
+/// before it reaches `data_path`/`metadata_path`, so a crafted `{hash}` can't be used to
+/// probe or read arbitrary paths under [`ARCHIVE_DIR`].
+fn is_valid_hash(hash: &str) -> bool {
+	hash.len() == 64 && hash.bytes().all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+	let mut hasher = Sha256::new();
+	hasher.update(bytes);
+	let digest = hasher.finalize();
+
+	let mut hex = String::with_capacity(digest.len() * 2);
+	for byte in digest {
+		write!(hex, "{byte:02x}").unwrap();
+	}
+	hex
+}
+
+/// Persists already-encoded scan bytes under a path keyed by their SHA-256 hex digest.
+/// Re-scanning identical content maps to the same hash, so storing is naturally
+/// de-duplicated: if the file is already there, only its metadata is (re)written.
+pub fn store(bytes: &[u8], format: OutputFormat, width: u32, height: u32) -> anyhow::Result<ArchiveMetadata> {
+	let dir = archive_dir()?;
+	let hash = sha256_hex(bytes);
+
+	let metadata = ArchiveMetadata {
+		hash: hash.clone(),
+		timestamp: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+		width,
+		height,
+		format: format.extension().to_string(),
+		size: bytes.len() as u64,
+	};
+
+	if !data_path(&dir, &hash).exists() {
+		fs::write(data_path(&dir, &hash), bytes)?;
+	}
+	fs::write(metadata_path(&dir, &hash), serde_json::to_vec_pretty(&metadata)?)?;
+
+	Ok(metadata)
+}
+
+fn fetch(hash: &str) -> anyhow::Result<Option<(Vec<u8>, ArchiveMetadata)>> {
+	if !is_valid_hash(hash) {
+		return Ok(None);
+	}
+
+	let dir = archive_dir()?;
+
+	let data = match fs::read(data_path(&dir, hash)) {
+		Ok(data) => data,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(e.into()),
+	};
+	let metadata = serde_json::from_slice(&fs::read(metadata_path(&dir, hash))?)?;
+
+	Ok(Some((data, metadata)))
+}
+
+fn list() -> anyhow::Result<Vec<ArchiveMetadata>> {
+	let dir = archive_dir()?;
+
+	let mut entries = Vec::new();
+	for entry in fs::read_dir(&dir)? {
+		let path = entry?.path();
+		if path.extension().and_then(|ext| ext.to_str()) != Some("json") {
+			continue;
+		}
+		entries.push(serde_json::from_slice(&fs::read(&path)?)?);
+	}
+	entries.sort_by(|a: &ArchiveMetadata, b: &ArchiveMetadata| b.timestamp.cmp(&a.timestamp));
+
+	Ok(entries)
+}
+
+#[get("/archive")]
+pub async fn list_service() -> Result<HttpResponse, ScanServiceError> {
+	let entries = tokio::task::spawn_blocking(list).await.map_err(|e| anyhow!(e))??;
+
+	Ok(HttpResponse::Ok().json(entries))
+}
+
+#[get("/archive/{hash}")]
+pub async fn get_service(hash: web::Path<String>) -> Result<HttpResponse, ScanServiceError> {
+	let hash = hash.into_inner();
+	let found = tokio::task::spawn_blocking(move || fetch(&hash))
+		.await
+		.map_err(|e| anyhow!(e))??;
+
+	let (data, metadata) = match found {
+		Some(found) => found,
+		None => return Ok(HttpResponse::NotFound().finish()),
+	};
+
+	let content_type = OutputFormat::from_extension(&metadata.format)
+		.map(OutputFormat::content_type)
+		.unwrap_or("application/octet-stream");
+
+	Ok(HttpResponse::Ok().content_type(content_type).body(data))
+}
@@ -1,12 +1,19 @@
 use actix_web::dev::{AnyBody, HttpServiceFactory};
+use actix_web::http::header::{HeaderValue, CACHE_CONTROL, ETAG};
 use actix_web::http::StatusCode;
 use actix_web::web::Bytes;
 use actix_web::{HttpRequest, HttpResponse, ResponseError};
 use log::debug;
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::fmt::Display;
+use std::hash::{Hash, Hasher};
 
-pub type FrontendFiles = HashMap<&'static str, &'static [u8]>;
+use crate::range;
+
+/// Maps a served path to its content (embedded at build time by `build.rs`) and the
+/// `Content-Type` derived from its extension.
+pub type FrontendFiles = HashMap<&'static str, (&'static [u8], &'static str)>;
 
 lazy_static::lazy_static! {
 	pub static ref  FRONTEND_FILES: FrontendFiles =
@@ -44,10 +51,35 @@ async fn serve_static_file(
 
 	debug!("Serving static file {:?}.", name);
 
-	let file = FRONTEND_FILES
+	let &(content, content_type) = FRONTEND_FILES
 		.get(name)
 		.ok_or(FrontendFilesServiceError::NotFound)?;
-	Ok(HttpResponse::Ok().body(Bytes::from_static(*file)))
+
+	let range_header = http_req
+		.headers()
+		.get(actix_web::http::header::RANGE)
+		.and_then(|v| v.to_str().ok());
+
+	let mut response = range::apply_range(Bytes::from_static(content), Some(content_type), range_header);
+
+	let headers = response.headers_mut();
+	headers.insert(ETAG, HeaderValue::from_str(&etag_for(content)).unwrap());
+	// The Nuxt build hashes these asset filenames, so their content never changes
+	// without the URL changing too; index.html itself isn't hashed and must revalidate.
+	if content_type != "text/html" {
+		headers.insert(
+			CACHE_CONTROL,
+			HeaderValue::from_static("public, max-age=31536000, immutable"),
+		);
+	}
+
+	Ok(response)
+}
+
+fn etag_for(content: &[u8]) -> String {
+	let mut hasher = DefaultHasher::new();
+	content.hash(&mut hasher);
+	format!("\"{:016x}\"", hasher.finish())
 }
 
 #[derive(Debug)]
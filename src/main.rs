@@ -3,18 +3,25 @@ use std::io::Write;
 use std::path::Path;
 
 use actix_web::web::Bytes;
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, ResponseError};
+use actix_web::{get, post, web, App, HttpRequest, HttpResponse, HttpServer, ResponseError};
 use anyhow::{anyhow, bail};
 use futures::task::Context;
 use futures::{Stream, StreamExt};
 use log::{debug, error, info, warn};
+use serde::Deserialize;
 use systemd_socket_activation::systemd_socket_activation;
 use tokio::macros::support::{Pin, Poll};
 use tokio::sync::mpsc::UnboundedReceiver;
 
 use sane_scan as sane;
 
+use output_format::OutputFormat;
+
+mod archive;
 mod frontend_files;
+mod output_format;
+mod range;
+mod scanner_api;
 
 macro_rules! try_or_send {
 	($v:expr, $sender:ident) => {
@@ -47,7 +54,7 @@ fn main() -> anyhow::Result<()> {
 }
 
 fn scan_to_file() -> anyhow::Result<()> {
-	let mut image = scan()?;
+	let mut image = scan(&ScanOptions::default())?;
 
 	info!("Scan completed. Saving to file.");
 
@@ -72,7 +79,14 @@ async fn run_webserver() -> anyhow::Result<()> {
 	let mut http_server = HttpServer::new(|| {
 		App::new()
 			.service(scan_service)
+			.service(scan_batch_service)
+			.service(scan_file_service)
 			.service(echo_service)
+			.service(scanner_api::list_devices)
+			.service(scanner_api::device_options)
+			.service(scanner_api::set_device_options)
+			.service(archive::list_service)
+			.service(archive::get_service)
 			.service(frontend_files::Service)
 	});
 
@@ -143,14 +157,130 @@ async fn echo_service(mut payload: web::Payload) -> Result<Bytes, actix_web::Err
 	Ok(body.freeze())
 }
 
-#[get("/scan.bmp")]
-async fn scan_service() -> Result<HttpResponse, ScanServiceError> {
+/// Shared by `/scan.{format}`, `/scan-file.{format}`, and `/scan-batch.pdf`; the latter
+/// ignores `format` since it always produces a PDF.
+#[derive(Deserialize)]
+struct ScanQuery {
+	format: Option<String>,
+	device: Option<usize>,
+	resolution: Option<i32>,
+	mode: Option<String>,
+	save: Option<bool>,
+}
+
+impl ScanQuery {
+	fn options(&self) -> ScanOptions {
+		ScanOptions {
+			device: self.device.unwrap_or_default(),
+			resolution: self.resolution,
+			mode: self.mode.clone(),
+			save: self.save.unwrap_or(false),
+		}
+	}
+}
+
+#[get("/scan.{format}")]
+async fn scan_service(
+	req: HttpRequest,
+	format_ext: web::Path<String>,
+	query: web::Query<ScanQuery>,
+) -> Result<HttpResponse, ScanServiceError> {
+	let accept = req
+		.headers()
+		.get(actix_web::http::header::ACCEPT)
+		.and_then(|v| v.to_str().ok());
+	let format = OutputFormat::resolve(&format_ext, query.format.as_deref(), accept)?;
+	let opts = query.options();
+
 	// TODO(aqatl): Possibly via a websocket?
-	let scan_stream = scan_stream_bmp().await;
+	if format == OutputFormat::Bmp {
+		let scan_stream = scan_stream_bmp(opts).await;
+		return Ok(HttpResponse::Ok()
+			.content_type(format.content_type())
+			.streaming(scan_stream));
+	}
+
+	let save = opts.save;
+	let image = tokio::task::spawn_blocking(move || scan(&opts))
+		.await
+		.map_err(|e| anyhow!(e))??;
+	let encoded = output_format::encode(&image, format)?;
+
+	if save {
+		let (encoded, width, height) = (encoded.clone(), image.width, image.height);
+		tokio::task::spawn_blocking(move || archive::store(&encoded, format, width, height))
+			.await
+			.map_err(|e| anyhow!(e))??;
+	}
+
+	Ok(HttpResponse::Ok()
+		.content_type(format.content_type())
+		.body(encoded))
+}
+
+/// Materializes the scan into memory instead of streaming it, so `Range` requests can be
+/// served, unlike `/scan.{format}`, which ignores `Range`. Since the encoded size is known once
+/// `output_format::encode` returns, this is a thin wrapper around `range::apply_range`.
+#[get("/scan-file.{format}")]
+async fn scan_file_service(
+	req: HttpRequest,
+	format_ext: web::Path<String>,
+	query: web::Query<ScanQuery>,
+) -> Result<HttpResponse, ScanServiceError> {
+	let accept = req
+		.headers()
+		.get(actix_web::http::header::ACCEPT)
+		.and_then(|v| v.to_str().ok());
+	let format = OutputFormat::resolve(&format_ext, query.format.as_deref(), accept)?;
+	let opts = query.options();
+
+	let save = opts.save;
+	let image = tokio::task::spawn_blocking(move || scan(&opts))
+		.await
+		.map_err(|e| anyhow!(e))??;
+	let encoded = output_format::encode(&image, format)?;
+
+	if save {
+		let (encoded, width, height) = (encoded.clone(), image.width, image.height);
+		tokio::task::spawn_blocking(move || archive::store(&encoded, format, width, height))
+			.await
+			.map_err(|e| anyhow!(e))??;
+	}
+
+	let range_header = req
+		.headers()
+		.get(actix_web::http::header::RANGE)
+		.and_then(|v| v.to_str().ok());
+
+	Ok(range::apply_range(
+		Bytes::from(encoded),
+		Some(format.content_type()),
+		range_header,
+	))
+}
+
+#[get("/scan-batch.pdf")]
+async fn scan_batch_service(
+	query: web::Query<ScanQuery>,
+) -> Result<HttpResponse, ScanServiceError> {
+	let opts = query.options();
+	let save = opts.save;
+	let pages = tokio::task::spawn_blocking(move || scan_batch(&opts))
+		.await
+		.map_err(|e| anyhow!(e))??;
+
+	let encoded = output_format::encode_pdf_pages(&pages, SCAN_DPI)?;
+
+	if save {
+		let (encoded, width, height) = (encoded.clone(), pages[0].width, pages[0].height);
+		tokio::task::spawn_blocking(move || archive::store(&encoded, OutputFormat::Pdf, width, height))
+			.await
+			.map_err(|e| anyhow!(e))??;
+	}
 
 	Ok(HttpResponse::Ok()
-		.content_type("image/bmp")
-		.streaming(scan_stream))
+		.content_type("application/pdf")
+		.body(encoded))
 }
 
 struct ScanImage {
@@ -159,20 +289,25 @@ struct ScanImage {
 	height: u32,
 }
 
-fn scan() -> anyhow::Result<ScanImage> {
-	let sane = sane::Sane::init_1_0()?;
-	let devices = sane.get_devices()?;
-	info!("devices: {:#?}", devices);
-
-	if devices.is_empty() {
-		bail!("No scanners found");
-	}
-
-	let mut handle = devices[0].open()?;
-
-	let device_options = handle.get_options()?;
-	info!("Device options: {:#?}", device_options);
+/// Device and option selection for a scan, sourced from `?device=`/`?resolution=`/`?mode=`
+/// query params. `device` indexes into `Sane::get_devices()`, matching the REST API's
+/// `/devices/{idx}` addressing.
+#[derive(Default, Clone)]
+struct ScanOptions {
+	device: usize,
+	resolution: Option<i32>,
+	mode: Option<String>,
+	save: bool,
+}
 
+/// Applies the requested resolution/mode onto `handle`, falling back to the previous
+/// hardcoded 300 DPI default when no resolution was requested. Used by `scan()`,
+/// `scan_batch()` and `scan_stream_bmp()`, and mirrors the option-setting `POST /devices/{idx}/options` does.
+fn apply_scan_options(
+	handle: &mut sane::Handle,
+	device_options: &[sane::DeviceOption],
+	opts: &ScanOptions,
+) -> anyhow::Result<()> {
 	if let Some(res_opt) = device_options
 		.iter()
 		.find(|opt| opt.name.to_bytes() == b"resolution")
@@ -182,17 +317,55 @@ fn scan() -> anyhow::Result<ScanImage> {
 				"Available resolutions: {:?}. Unit: {:?}",
 				resolutions, res_opt.unit
 			);
-			if matches!(res_opt.unit, sane::sys::Unit::Dpi) && resolutions.contains(&300) {
-				info!("Setting resolution to 300 DPI");
-				let info = handle.set_option(res_opt, sane::DeviceOptionValue::Int(300))?;
+			let target_resolution = opts.resolution.unwrap_or(300);
+			if matches!(res_opt.unit, sane::sys::Unit::Dpi) && resolutions.contains(&target_resolution) {
+				info!("Setting resolution to {} DPI", target_resolution);
+				let info = handle.set_option(res_opt, sane::DeviceOptionValue::Int(target_resolution))?;
 				info!("Returned info: {:#?}.", info);
 
 				let new_res = handle.get_option(res_opt)?;
 				info!("Resolution set to {:?} {:?}", new_res, res_opt.unit);
+			} else if opts.resolution.is_some() {
+				bail!("Unsupported resolution: {}", target_resolution);
 			}
 		}
 	}
 
+	if let Some(ref mode) = opts.mode {
+		let mode_opt = device_options
+			.iter()
+			.find(|opt| opt.name.to_bytes() == b"mode")
+			.ok_or_else(|| anyhow!("Device has no \"mode\" option"))?;
+		if let sane::OptionConstraint::StringList(ref modes) = mode_opt.constraint {
+			if !modes.iter().any(|m| m.to_bytes() == mode.as_bytes()) {
+				bail!("Unsupported scan mode: {}", mode);
+			}
+		}
+		info!("Setting mode to {:?}", mode);
+		handle.set_option(
+			mode_opt,
+			sane::DeviceOptionValue::String(std::ffi::CString::new(mode.as_str())?),
+		)?;
+	}
+
+	Ok(())
+}
+
+fn scan(opts: &ScanOptions) -> anyhow::Result<ScanImage> {
+	let sane = sane::Sane::init_1_0()?;
+	let devices = sane.get_devices()?;
+	info!("devices: {:#?}", devices);
+
+	let device = devices
+		.get(opts.device)
+		.ok_or_else(|| anyhow!("No such device: {}", opts.device))?;
+	let mut handle = device.open()?;
+
+	let device_options = handle.get_options()?;
+	info!("Device options: {:#?}", device_options);
+
+	apply_scan_options(&mut handle, &device_options, opts)?;
+
 	let parameters = handle.start_scan()?;
 
 	let width = parameters.pixels_per_line as u32;
@@ -209,45 +382,124 @@ fn scan() -> anyhow::Result<ScanImage> {
 	})
 }
 
-async fn scan_stream_bmp() -> StreamingReceiver<Result<Bytes, anyhow::Error>> {
+const ADF_SOURCE: &str = "Automatic Document Feeder";
+
+/// Switches the `source` option to the ADF when the device has it, so `scan_batch` pulls
+/// from the feeder instead of the flatbed. Returns whether the ADF was actually selected;
+/// devices without one are left untouched and the caller must stop after a single page,
+/// since a flatbed never reports "out of paper".
+fn select_adf_source(handle: &mut sane::Handle, device_options: &[sane::DeviceOption]) -> anyhow::Result<bool> {
+	let source_opt = match device_options
+		.iter()
+		.find(|opt| opt.name.to_bytes() == b"source")
+	{
+		Some(opt) => opt,
+		None => {
+			warn!("Device has no \"source\" option; scanning a single page");
+			return Ok(false);
+		}
+	};
+
+	if let sane::OptionConstraint::StringList(ref sources) = source_opt.constraint {
+		if !sources.iter().any(|s| s.to_bytes() == ADF_SOURCE.as_bytes()) {
+			warn!(
+				"Device has no \"{}\" source; scanning a single page",
+				ADF_SOURCE
+			);
+			return Ok(false);
+		}
+	}
+
+	info!("Selecting {:?} as scan source", ADF_SOURCE);
+	handle.set_option(
+		source_opt,
+		sane::DeviceOptionValue::String(std::ffi::CString::new(ADF_SOURCE)?),
+	)?;
+
+	Ok(true)
+}
+
+fn is_out_of_paper(err: &sane::Error) -> bool {
+	matches!(err, sane::Error::Status(sane::sys::Status::NoDocs))
+}
+
+/// Scans every page the ADF has loaded into one `Vec<ScanImage>`, stopping as soon as the
+/// feeder reports it's out of paper. That "no more documents" status is the expected way
+/// a batch ends, not an error. Devices without an ADF don't have a concept of "out of
+/// paper" at all (a flatbed never returns it), so those scan exactly one page.
+fn scan_batch(opts: &ScanOptions) -> anyhow::Result<Vec<ScanImage>> {
+	let sane = sane::Sane::init_1_0()?;
+	let devices = sane.get_devices()?;
+	info!("devices: {:#?}", devices);
+
+	let device = devices
+		.get(opts.device)
+		.ok_or_else(|| anyhow!("No such device: {}", opts.device))?;
+	let mut handle = device.open()?;
+
+	let device_options = handle.get_options()?;
+	info!("Device options: {:#?}", device_options);
+
+	apply_scan_options(&mut handle, &device_options, opts)?;
+	let adf_engaged = select_adf_source(&mut handle, &device_options)?;
+
+	let mut pages = Vec::new();
+	loop {
+		let parameters = match handle.start_scan() {
+			Ok(parameters) => parameters,
+			Err(e) if is_out_of_paper(&e) => break,
+			Err(e) => return Err(e.into()),
+		};
+
+		let width = parameters.pixels_per_line as u32;
+		let height = parameters.lines as u32;
+
+		let image = handle.read_to_vec()?;
+
+		info!("Scanned page {} ({}x{}).", pages.len() + 1, width, height);
+		pages.push(ScanImage {
+			raw_data: image,
+			width,
+			height,
+		});
+
+		handle.cancel_scan()?;
+
+		if !adf_engaged {
+			break;
+		}
+	}
+
+	if pages.is_empty() {
+		bail!("No pages scanned");
+	}
+
+	Ok(pages)
+}
+
+async fn scan_stream_bmp(opts: ScanOptions) -> StreamingReceiver<Result<Bytes, anyhow::Error>> {
 	let (sender, receiver) = tokio::sync::mpsc::unbounded_channel::<Result<Bytes, anyhow::Error>>();
 
 	tokio::task::spawn_blocking(move || {
 		let libsane: sane::Sane = try_or_send!(sane::Sane::init_1_0(), sender);
 		let devices: Vec<sane::Device> = try_or_send!(libsane.get_devices(), sender);
 
-		if devices.is_empty() {
-			sender.send(Err(anyhow!("No scanners found."))).unwrap();
-			return;
-		}
+		let device = match devices.get(opts.device) {
+			Some(device) => device,
+			None => {
+				sender
+					.send(Err(anyhow!("No such device: {}", opts.device)))
+					.unwrap();
+				return;
+			}
+		};
 
-		let mut handle = try_or_send!(devices[0].open(), sender);
+		let mut handle = try_or_send!(device.open(), sender);
 
 		let device_options = try_or_send!(handle.get_options(), sender);
 		info!("Device options: {:#?}", device_options);
 
-		if let Some(res_opt) = device_options
-			.iter()
-			.find(|opt| opt.name.to_bytes() == b"resolution")
-		{
-			if let sane::OptionConstraint::WordList(ref resolutions) = res_opt.constraint {
-				info!(
-					"Available resolutions: {:?}. Unit: {:?}",
-					resolutions, res_opt.unit
-				);
-				if matches!(res_opt.unit, sane::sys::Unit::Dpi) && resolutions.contains(&300) {
-					info!("Setting resolution to 300 DPI");
-					let info = try_or_send!(
-						handle.set_option(res_opt, sane::DeviceOptionValue::Int(300)),
-						sender
-					);
-					info!("Returned info: {:#?}.", info);
-
-					let new_res = try_or_send!(handle.get_option(res_opt), sender);
-					info!("Resolution set to {:?} {:?}", new_res, res_opt.unit);
-				}
-			}
-		}
+		try_or_send!(apply_scan_options(&mut handle, &device_options, &opts), sender);
 
 		let parameters = try_or_send!(handle.start_scan(), sender);
 
@@ -282,6 +534,10 @@ async fn scan_stream_bmp() -> StreamingReceiver<Result<Bytes, anyhow::Error>> {
 			encode_as_bmp(width * height * 3, (width, height), &mut bmp_header),
 			sender
 		);
+
+		// Tee every chunk we send to the client into `save_buf` as it's produced, so
+		// `?save=true` doesn't need a second pass over the (potentially huge) BMP.
+		let mut save_buf = opts.save.then(|| bmp_header.clone());
 		sender.send(Ok(Bytes::from(bmp_header))).unwrap();
 
 		// Vector capacity must be divisible by 3, so that we always get full pixels. Otherwise, we'd
@@ -295,8 +551,17 @@ async fn scan_stream_bmp() -> StreamingReceiver<Result<Bytes, anyhow::Error>> {
 		while let Ok(Some(written)) = handle.read(buf.as_mut_slice()) {
 			let mut cloned_buf = (&buf[0..written]).to_vec();
 			rgb_to_bgr(&mut cloned_buf);
+			if let Some(save_buf) = save_buf.as_mut() {
+				save_buf.extend_from_slice(&cloned_buf);
+			}
 			sender.send(Ok(Bytes::from(cloned_buf))).unwrap();
 		}
+
+		if let Some(save_buf) = save_buf {
+			if let Err(e) = archive::store(&save_buf, OutputFormat::Bmp, width, height) {
+				error!("Failed to save scan to archive: {:?}", e);
+			}
+		}
 	});
 	StreamingReceiver(receiver)
 }
@@ -311,6 +576,8 @@ impl<T> Stream for StreamingReceiver<T> {
 	}
 }
 
+const SCAN_DPI: f64 = 300.0;
+
 const BMP_FILE_HEADER_SIZE: u32 = 2 + 4 + 2 + 2 + 4;
 const BMP_IMAGE_HEADER_SIZE: u32 = 4 + 4 + 4 + 2 + 2 + 4 + 4 + 4 + 4 + 4 + 4;
 const BMP_HEADER_SIZE: u32 = BMP_FILE_HEADER_SIZE + BMP_IMAGE_HEADER_SIZE;
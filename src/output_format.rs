@@ -0,0 +1,273 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::{ColorType, ImageEncoder};
+
+use anyhow::{anyhow, bail};
+
+use crate::{encode_as_bmp, rgb_to_bgr, ScanImage, SCAN_DPI};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputFormat {
+	Bmp,
+	Png,
+	Jpeg,
+	Tiff,
+	Pdf,
+}
+
+impl OutputFormat {
+	pub fn content_type(self) -> &'static str {
+		match self {
+			OutputFormat::Bmp => "image/bmp",
+			OutputFormat::Png => "image/png",
+			OutputFormat::Jpeg => "image/jpeg",
+			OutputFormat::Tiff => "image/tiff",
+			OutputFormat::Pdf => "application/pdf",
+		}
+	}
+
+	pub fn extension(self) -> &'static str {
+		match self {
+			OutputFormat::Bmp => "bmp",
+			OutputFormat::Png => "png",
+			OutputFormat::Jpeg => "jpeg",
+			OutputFormat::Tiff => "tiff",
+			OutputFormat::Pdf => "pdf",
+		}
+	}
+
+	pub fn from_extension(ext: &str) -> Option<Self> {
+		match ext {
+			"bmp" => Some(OutputFormat::Bmp),
+			"png" => Some(OutputFormat::Png),
+			"jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+			"tif" | "tiff" => Some(OutputFormat::Tiff),
+			"pdf" => Some(OutputFormat::Pdf),
+			_ => None,
+		}
+	}
+
+	pub fn from_mime(mime: &str) -> Option<Self> {
+		match mime.split(';').next().unwrap_or(mime).trim() {
+			"image/bmp" => Some(OutputFormat::Bmp),
+			"image/png" => Some(OutputFormat::Png),
+			"image/jpeg" => Some(OutputFormat::Jpeg),
+			"image/tiff" => Some(OutputFormat::Tiff),
+			"application/pdf" => Some(OutputFormat::Pdf),
+			_ => None,
+		}
+	}
+
+	/// Resolves the format for a `/scan.<ext>` request. An explicit `?format=` query
+	/// param or the `Accept` header take priority over the path extension, so a client
+	/// can e.g. hit plain `/scan.bmp` but ask for PNG via `Accept: image/png`.
+	pub fn resolve(
+		path_ext: &str,
+		query_format: Option<&str>,
+		accept: Option<&str>,
+	) -> anyhow::Result<Self> {
+		if let Some(format) = query_format.and_then(Self::from_extension) {
+			return Ok(format);
+		}
+		if let Some(format) = accept.and_then(Self::from_mime) {
+			return Ok(format);
+		}
+		Self::from_extension(path_ext).ok_or_else(|| anyhow!("Unsupported scan format {:?}", path_ext))
+	}
+}
+
+pub fn encode(image: &ScanImage, format: OutputFormat) -> anyhow::Result<Vec<u8>> {
+	match format {
+		OutputFormat::Bmp => {
+			let mut bgr = image.raw_data.clone();
+			rgb_to_bgr(&mut bgr);
+			let mut out = Vec::new();
+			encode_as_bmp(&bgr, (image.width, image.height), &mut out)?;
+			Ok(out)
+		}
+		OutputFormat::Png => encode_with_image_crate(image, image::ImageFormat::Png),
+		OutputFormat::Tiff => encode_with_image_crate(image, image::ImageFormat::Tiff),
+		OutputFormat::Jpeg => encode_jpeg(image, JPEG_QUALITY),
+		OutputFormat::Pdf => encode_pdf(image),
+	}
+}
+
+const JPEG_QUALITY: u8 = 90;
+
+fn encode_with_image_crate(image: &ScanImage, format: image::ImageFormat) -> anyhow::Result<Vec<u8>> {
+	let mut out = Vec::new();
+	image::write_buffer_with_format(
+		&mut std::io::Cursor::new(&mut out),
+		&image.raw_data,
+		image.width,
+		image.height,
+		ColorType::Rgb8,
+		format,
+	)?;
+	Ok(out)
+}
+
+fn encode_jpeg(image: &ScanImage, quality: u8) -> anyhow::Result<Vec<u8>> {
+	let mut out = Vec::new();
+	JpegEncoder::new_with_quality(&mut out, quality).write_image(
+		&image.raw_data,
+		image.width,
+		image.height,
+		ColorType::Rgb8,
+	)?;
+	Ok(out)
+}
+
+fn encode_pdf(image: &ScanImage) -> anyhow::Result<Vec<u8>> {
+	encode_pdf_pages(std::slice::from_ref(image), SCAN_DPI)
+}
+
+/// Hand-rolls a minimal PDF with one page per scanned image, each wrapping a
+/// `/DCTDecode` JPEG XObject sized to fill the page. `dpi` converts an image's pixel
+/// dimensions to the page's point dimensions (points = pixels * 72 / dpi).
+pub fn encode_pdf_pages(images: &[ScanImage], dpi: f64) -> anyhow::Result<Vec<u8>> {
+	if images.is_empty() {
+		bail!("Cannot build a PDF with no pages");
+	}
+
+	let pages = images
+		.iter()
+		.map(|image| Ok((encode_jpeg(image, JPEG_QUALITY)?, image.width, image.height)))
+		.collect::<anyhow::Result<Vec<(Vec<u8>, u32, u32)>>>()?;
+
+	write_pdf(&pages, dpi)
+}
+
+/// Object numbering: 1 Catalog, 2 Pages, then 3 objects per page (Page, Contents,
+/// Image XObject), so page `i`'s Page object is `3 + 3*i`.
+fn write_pdf(pages: &[(Vec<u8>, u32, u32)], dpi: f64) -> anyhow::Result<Vec<u8>> {
+	let page_obj = |i: usize| 3 + 3 * i as u32;
+
+	let mut out = Vec::new();
+	let mut offsets = Vec::new();
+
+	out.extend_from_slice(b"%PDF-1.7\n");
+
+	offsets.push(out.len());
+	out.extend_from_slice(b"1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n");
+
+	let kids = (0..pages.len())
+		.map(|i| format!("{} 0 R", page_obj(i)))
+		.collect::<Vec<_>>()
+		.join(" ");
+	offsets.push(out.len());
+	out.extend_from_slice(
+		format!(
+			"2 0 obj\n<< /Type /Pages /Kids [{kids}] /Count {} >>\nendobj\n",
+			pages.len()
+		)
+		.as_bytes(),
+	);
+
+	for (i, (jpeg, width, height)) in pages.iter().enumerate() {
+		let width_pt = *width as f64 * 72.0 / dpi;
+		let height_pt = *height as f64 * 72.0 / dpi;
+		let content = format!("q {width_pt:.2} 0 0 {height_pt:.2} 0 0 cm /Im0 Do Q");
+
+		let page = page_obj(i);
+		let contents = page + 1;
+		let image_obj = page + 2;
+
+		offsets.push(out.len());
+		out.extend_from_slice(
+			format!(
+				"{page} 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width_pt:.2} {height_pt:.2}] \
+				 /Resources << /XObject << /Im0 {image_obj} 0 R >> >> /Contents {contents} 0 R >>\nendobj\n"
+			)
+			.as_bytes(),
+		);
+
+		offsets.push(out.len());
+		out.extend_from_slice(
+			format!(
+				"{contents} 0 obj\n<< /Length {} >>\nstream\n{content}\nendstream\nendobj\n",
+				content.len()
+			)
+			.as_bytes(),
+		);
+
+		offsets.push(out.len());
+		out.extend_from_slice(
+			format!(
+				"{image_obj} 0 obj\n<< /Type /XObject /Subtype /Image /Width {width} /Height {height} \
+				 /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /DCTDecode /Length {} >>\nstream\n",
+				jpeg.len()
+			)
+			.as_bytes(),
+		);
+		out.extend_from_slice(jpeg);
+		out.extend_from_slice(b"\nendstream\nendobj\n");
+	}
+
+	let xref_offset = out.len();
+	out.extend_from_slice(format!("xref\n0 {}\n", offsets.len() + 1).as_bytes());
+	out.extend_from_slice(b"0000000000 65535 f \n");
+	for offset in &offsets {
+		out.extend_from_slice(format!("{offset:010} 00000 n \n").as_bytes());
+	}
+	out.extend_from_slice(
+		format!(
+			"trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF",
+			offsets.len() + 1
+		)
+		.as_bytes(),
+	);
+
+	Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Each offset recorded in the xref table must point exactly at the start of its
+	/// `N 0 obj` marker, for every object in both a single-page and a multi-page PDF.
+	fn assert_offsets_point_to_objects(pdf: &[u8], object_count: usize) {
+		let text = String::from_utf8_lossy(pdf);
+		for obj_num in 1..=object_count {
+			let offset = offsets_for(&text)[obj_num - 1];
+			let marker = format!("{obj_num} 0 obj");
+			assert_eq!(
+				&pdf[offset..offset + marker.len()],
+				marker.as_bytes(),
+				"object {obj_num}'s xref offset doesn't point at its own marker"
+			);
+		}
+	}
+
+	fn offsets_for(text: &str) -> Vec<usize> {
+		text.lines()
+			.skip_while(|line| *line != "xref")
+			.skip(3)
+			.take_while(|line| !line.starts_with("trailer"))
+			.map(|line| line[..10].parse().unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn single_page_xref_offsets_are_correct() {
+		let pages = vec![(vec![0xFFu8, 0xD8, 0xFF, 0xD9], 100, 200)];
+		let pdf = write_pdf(&pages, 300.0).unwrap();
+		assert_offsets_point_to_objects(&pdf, 5);
+	}
+
+	#[test]
+	fn multi_page_xref_offsets_are_correct() {
+		let pages = vec![
+			(vec![0xFFu8, 0xD8, 0xFF, 0xD9], 100, 200),
+			(vec![0xFFu8, 0xD8, 0x01, 0x02, 0xFF, 0xD9], 150, 250),
+			(vec![0xFFu8, 0xD8, 0xFF, 0xD9], 100, 200),
+		];
+		let pdf = write_pdf(&pages, 300.0).unwrap();
+		assert_offsets_point_to_objects(&pdf, 11);
+	}
+
+	#[test]
+	fn rejects_empty_page_list() {
+		assert!(encode_pdf_pages(&[], SCAN_DPI).is_err());
+	}
+}
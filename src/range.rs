@@ -0,0 +1,148 @@
+use actix_web::web::Bytes;
+use actix_web::HttpResponse;
+
+#[derive(Debug)]
+pub struct ByteRange {
+	pub start: u64,
+	pub end: u64,
+}
+
+#[derive(Debug)]
+pub enum RangeError {
+	Unsatisfiable,
+}
+
+/// Parses a `Range: bytes=...` header value, supporting `start-end`, open-ended
+/// `start-`, and suffix `-n` forms. Only the first range in the header is honored;
+/// multi-range (`multipart/byteranges`) responses aren't implemented. Returns
+/// `Ok(None)` for anything that isn't a `bytes=` range, falling back to a normal 200
+/// response, and `Err(RangeError::Unsatisfiable)` when the requested range is out of
+/// bounds.
+pub fn parse_range(header: &str, total: u64) -> Result<Option<ByteRange>, RangeError> {
+	let spec = match header.strip_prefix("bytes=") {
+		Some(spec) => spec,
+		None => return Ok(None),
+	};
+	let spec = spec.split(',').next().unwrap_or(spec).trim();
+
+	let (start, end) = match spec.split_once('-') {
+		Some(("", suffix)) => {
+			let suffix_len: u64 = suffix.parse().map_err(|_| RangeError::Unsatisfiable)?;
+			if suffix_len == 0 || total == 0 {
+				return Err(RangeError::Unsatisfiable);
+			}
+			(total.saturating_sub(suffix_len), total - 1)
+		}
+		Some((start, "")) => {
+			let start: u64 = start.parse().map_err(|_| RangeError::Unsatisfiable)?;
+			(start, total.saturating_sub(1))
+		}
+		Some((start, end)) => {
+			let start: u64 = start.parse().map_err(|_| RangeError::Unsatisfiable)?;
+			let end: u64 = end.parse().map_err(|_| RangeError::Unsatisfiable)?;
+			(start, end)
+		}
+		None => return Err(RangeError::Unsatisfiable),
+	};
+
+	if total == 0 || start >= total || start > end {
+		return Err(RangeError::Unsatisfiable);
+	}
+
+	Ok(Some(ByteRange {
+		start,
+		end: end.min(total - 1),
+	}))
+}
+
+/// Builds a 200, 206, or 416 response for `body` depending on `range_header`. Shared by
+/// `frontend_files::serve_static_file` and the materialized scan endpoint, since both
+/// serve an in-memory byte slice whose total length is known up front.
+pub fn apply_range(body: Bytes, content_type: Option<&str>, range_header: Option<&str>) -> HttpResponse {
+	let total = body.len() as u64;
+
+	let range = match range_header.map(|header| parse_range(header, total)) {
+		None => None,
+		Some(Ok(range)) => range,
+		Some(Err(RangeError::Unsatisfiable)) => {
+			return HttpResponse::RangeNotSatisfiable()
+				.insert_header(("Content-Range", format!("bytes */{total}")))
+				.finish();
+		}
+	};
+
+	let mut response = match range {
+		None => HttpResponse::Ok(),
+		Some(_) => HttpResponse::PartialContent(),
+	};
+	response.insert_header(("Accept-Ranges", "bytes"));
+	if let Some(content_type) = content_type {
+		response.content_type(content_type);
+	}
+
+	match range {
+		None => response.body(body),
+		Some(ByteRange { start, end }) => {
+			response.insert_header(("Content-Range", format!("bytes {start}-{end}/{total}")));
+			response.body(body.slice(start as usize..=end as usize))
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_start_end() {
+		let range = parse_range("bytes=0-99", 200).unwrap().unwrap();
+		assert_eq!(range.start, 0);
+		assert_eq!(range.end, 99);
+	}
+
+	#[test]
+	fn parses_open_ended_start() {
+		let range = parse_range("bytes=150-", 200).unwrap().unwrap();
+		assert_eq!(range.start, 150);
+		assert_eq!(range.end, 199);
+	}
+
+	#[test]
+	fn parses_suffix_length() {
+		let range = parse_range("bytes=-50", 200).unwrap().unwrap();
+		assert_eq!(range.start, 150);
+		assert_eq!(range.end, 199);
+	}
+
+	#[test]
+	fn clamps_end_past_total() {
+		let range = parse_range("bytes=0-1000", 200).unwrap().unwrap();
+		assert_eq!(range.start, 0);
+		assert_eq!(range.end, 199);
+	}
+
+	#[test]
+	fn rejects_start_past_total() {
+		assert!(matches!(parse_range("bytes=200-", 200), Err(RangeError::Unsatisfiable)));
+	}
+
+	#[test]
+	fn rejects_start_after_end() {
+		assert!(matches!(parse_range("bytes=100-50", 200), Err(RangeError::Unsatisfiable)));
+	}
+
+	#[test]
+	fn rejects_zero_length_suffix() {
+		assert!(matches!(parse_range("bytes=-0", 200), Err(RangeError::Unsatisfiable)));
+	}
+
+	#[test]
+	fn rejects_on_empty_body() {
+		assert!(matches!(parse_range("bytes=0-", 0), Err(RangeError::Unsatisfiable)));
+	}
+
+	#[test]
+	fn ignores_non_bytes_unit() {
+		assert!(parse_range("items=0-5", 200).unwrap().is_none());
+	}
+}
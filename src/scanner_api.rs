@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::ffi::CString;
+
+use actix_web::{get, post, web, HttpResponse};
+use anyhow::{anyhow, bail};
+use log::info;
+use serde::Serialize;
+use serde_json::Value;
+
+use sane_scan as sane;
+
+use crate::ScanServiceError;
+
+#[derive(Serialize)]
+struct DeviceInfo {
+	name: String,
+	vendor: String,
+	model: String,
+	#[serde(rename = "type")]
+	type_: String,
+}
+
+#[get("/devices")]
+async fn list_devices() -> Result<HttpResponse, ScanServiceError> {
+	let devices = tokio::task::spawn_blocking(list_devices_blocking)
+		.await
+		.map_err(|e| anyhow!(e))??;
+
+	Ok(HttpResponse::Ok().json(devices))
+}
+
+fn list_devices_blocking() -> anyhow::Result<Vec<DeviceInfo>> {
+	let libsane = sane::Sane::init_1_0()?;
+	let devices = libsane.get_devices()?;
+
+	Ok(devices
+		.iter()
+		.map(|device| DeviceInfo {
+			name: device.name.to_string_lossy().into_owned(),
+			vendor: device.vendor.to_string_lossy().into_owned(),
+			model: device.model.to_string_lossy().into_owned(),
+			type_: device.type_.to_string_lossy().into_owned(),
+		})
+		.collect())
+}
+
+#[derive(Serialize)]
+struct OptionInfo {
+	name: String,
+	title: String,
+	desc: String,
+	#[serde(rename = "type")]
+	type_: String,
+	unit: String,
+	constraint: ConstraintInfo,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum ConstraintInfo {
+	None,
+	Range { min: i32, max: i32, quant: i32 },
+	WordList(Vec<i32>),
+	StringList(Vec<String>),
+}
+
+impl From<&sane::DeviceOption> for OptionInfo {
+	fn from(option: &sane::DeviceOption) -> Self {
+		let constraint = match &option.constraint {
+			sane::OptionConstraint::None => ConstraintInfo::None,
+			sane::OptionConstraint::Range { range, quant } => ConstraintInfo::Range {
+				min: range.start,
+				max: range.end,
+				quant: *quant,
+			},
+			sane::OptionConstraint::WordList(list) => ConstraintInfo::WordList(list.clone()),
+			sane::OptionConstraint::StringList(list) => {
+				ConstraintInfo::StringList(list.iter().map(|s| s.to_string_lossy().into_owned()).collect())
+			}
+		};
+
+		OptionInfo {
+			name: option.name.to_string_lossy().into_owned(),
+			title: option.title.to_string_lossy().into_owned(),
+			desc: option.desc.to_string_lossy().into_owned(),
+			type_: format!("{:?}", option.type_),
+			unit: format!("{:?}", option.unit),
+			constraint,
+		}
+	}
+}
+
+#[get("/devices/{idx}/options")]
+async fn device_options(idx: web::Path<usize>) -> Result<HttpResponse, ScanServiceError> {
+	let idx = *idx;
+	let options = tokio::task::spawn_blocking(move || device_options_blocking(idx))
+		.await
+		.map_err(|e| anyhow!(e))??;
+
+	Ok(HttpResponse::Ok().json(options))
+}
+
+fn device_options_blocking(idx: usize) -> anyhow::Result<Vec<OptionInfo>> {
+	let libsane = sane::Sane::init_1_0()?;
+	let devices = libsane.get_devices()?;
+	let device = devices.get(idx).ok_or_else(|| anyhow!("No such device: {}", idx))?;
+	let mut handle = device.open()?;
+
+	let options = handle.get_options()?;
+	info!("Device options: {:#?}", options);
+
+	Ok(options.iter().map(OptionInfo::from).collect())
+}
+
+#[post("/devices/{idx}/options")]
+async fn set_device_options(
+	idx: web::Path<usize>,
+	body: web::Json<HashMap<String, Value>>,
+) -> Result<HttpResponse, ScanServiceError> {
+	let idx = *idx;
+	let requested = body.into_inner();
+	tokio::task::spawn_blocking(move || set_device_options_blocking(idx, requested))
+		.await
+		.map_err(|e| anyhow!(e))??;
+
+	Ok(HttpResponse::Ok().finish())
+}
+
+fn set_device_options_blocking(idx: usize, requested: HashMap<String, Value>) -> anyhow::Result<()> {
+	let libsane = sane::Sane::init_1_0()?;
+	let devices = libsane.get_devices()?;
+	let device = devices.get(idx).ok_or_else(|| anyhow!("No such device: {}", idx))?;
+	let mut handle = device.open()?;
+
+	let options = handle.get_options()?;
+
+	for (name, value) in requested {
+		let option = options
+			.iter()
+			.find(|opt| opt.name.to_bytes() == name.as_bytes())
+			.ok_or_else(|| anyhow!("Unknown option: {}", name))?;
+
+		let value = match value {
+			Value::Number(n) => {
+				let n = n
+					.as_i64()
+					.ok_or_else(|| anyhow!("Invalid integer value for option {}", name))?;
+				sane::DeviceOptionValue::Int(n as i32)
+			}
+			Value::String(s) => sane::DeviceOptionValue::String(CString::new(s)?),
+			other => bail!("Unsupported value for option {}: {:?}", name, other),
+		};
+
+		let info = handle.set_option(option, value)?;
+		info!("Set option {:?}, returned info: {:#?}.", name, info);
+	}
+
+	Ok(())
+}